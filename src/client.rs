@@ -0,0 +1,80 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::TcpStream;
+
+use crate::{Command, LockMode, Response};
+
+/// A typed client for the mutex server's newline-delimited JSON protocol,
+/// letting other Rust programs (and this crate's integration tests) talk to
+/// `Server` without hand-rolling sockets.
+pub struct Client {
+    stream: TcpStream,
+    reader: BufReader<TcpStream>,
+}
+
+impl Client {
+    pub fn connect(addr: &str) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        let reader = BufReader::new(stream.try_clone()?);
+        Ok(Self { stream, reader })
+    }
+
+    pub fn lock(
+        &mut self,
+        id: &str,
+        timeout_ms: Option<u64>,
+        lease_ms: Option<u64>,
+        mode: LockMode,
+    ) -> io::Result<bool> {
+        match self.send(&Command::Lock {
+            id: id.to_string(),
+            timeout_ms,
+            lease_ms,
+            mode,
+        })? {
+            Response::LockResponse { success, .. } => Ok(success),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    pub fn renew(&mut self, id: &str, lease_ms: Option<u64>) -> io::Result<bool> {
+        match self.send(&Command::Renew {
+            id: id.to_string(),
+            lease_ms,
+        })? {
+            Response::RenewResponse { success, .. } => Ok(success),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    pub fn release(&mut self, id: &str) -> io::Result<bool> {
+        match self.send(&Command::Release { id: id.to_string() })? {
+            Response::ReleaseResponse { success, .. } => Ok(success),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    pub fn check(&mut self, id: &str) -> io::Result<(Option<LockMode>, usize)> {
+        match self.send(&Command::Check { id: id.to_string() })? {
+            Response::CheckResponse { mode, holders, .. } => Ok((mode, holders)),
+            other => Err(unexpected(other)),
+        }
+    }
+
+    fn send(&mut self, command: &Command) -> io::Result<Response> {
+        let payload = serde_json::to_string(command).unwrap() + "\n";
+        self.stream.write_all(payload.as_bytes())?;
+        self.stream.flush()?;
+
+        let mut line = String::new();
+        self.reader.read_line(&mut line)?;
+        serde_json::from_str(line.trim_end()).map_err(io::Error::from)
+    }
+}
+
+fn unexpected(response: Response) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unexpected response: {:?}", response),
+    )
+}
@@ -0,0 +1,734 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{Read, Write},
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use native_tls::TlsAcceptor;
+use serde::{Deserialize, Serialize};
+
+pub mod client;
+
+pub use client::Client;
+
+type ConnectionID = usize;
+
+/// A byte stream a `Connection` can read commands from and write responses
+/// to — either a plain `TcpStream` or a TLS-wrapped one.
+trait Transport: Read + Write + Send {}
+impl<T: Read + Write + Send> Transport for T {}
+
+/// Longest newline-delimited command line we'll buffer before giving up on a
+/// connection; guards against an unbounded `read_buf` from a runaway client.
+const MAX_LINE_BYTES: usize = 64 * 1024;
+
+/// How often the background reaper scans for expired leases.
+const LEASE_REAP_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Whether a `Lock` request wants sole access (`Exclusive`) or is willing to
+/// coexist with other readers (`Shared`), mirroring `std::sync::RwLock`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    Shared,
+    Exclusive,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "command", content = "params")]
+pub enum Command {
+    Lock {
+        id: MutexID,
+        timeout_ms: Option<u64>,
+        lease_ms: Option<u64>,
+        mode: LockMode,
+    },
+    Renew {
+        id: MutexID,
+        lease_ms: Option<u64>,
+    },
+    Check {
+        id: MutexID,
+    },
+    Release {
+        id: MutexID,
+    },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "command", content = "params")]
+pub enum Response {
+    LockResponse {
+        id: MutexID,
+        success: bool,
+    },
+    RenewResponse {
+        id: MutexID,
+        success: bool,
+    },
+    ReleaseResponse {
+        id: MutexID,
+        success: bool,
+    },
+    CheckResponse {
+        id: MutexID,
+        mode: Option<LockMode>,
+        holders: usize,
+    },
+    Error {
+        message: String,
+    },
+}
+struct Connection {
+    pub id: ConnectionID,
+    pub stream: Box<dyn Transport>,
+    pub locks: HashMap<MutexID, LockMode>,
+    state: Server,
+    is_alive: bool,
+    read_buf: Vec<u8>,
+}
+
+impl Connection {
+    fn new(state: Server, id: usize, stream: Box<dyn Transport>) -> Self {
+        Self {
+            id,
+            stream,
+            state,
+            is_alive: true,
+            locks: Default::default(),
+            read_buf: Vec::new(),
+        }
+    }
+    fn cycle(&mut self) {
+        let mut chunk = [0; 1024];
+        while self.is_alive {
+            let count = self.stream.read(&mut chunk).unwrap_or(0);
+            if count == 0 {
+                // println!("Connection closed");
+                break;
+            }
+            self.read_buf.extend_from_slice(&chunk[0..count]);
+            self.process_buffer();
+        }
+        self.release_all();
+    }
+    /// Splits `read_buf` on `\n`, handling each complete line as a frame and
+    /// leaving any trailing partial command for the next read.
+    fn process_buffer(&mut self) {
+        while let Some(pos) = self.read_buf.iter().position(|&b| b == b'\n') {
+            let line = self.read_buf.drain(..=pos).collect::<Vec<u8>>();
+            let line = &line[..line.len() - 1];
+            self.handle_line(line);
+            if !self.is_alive {
+                return;
+            }
+        }
+
+        if self.read_buf.len() > MAX_LINE_BYTES {
+            eprintln!(
+                "Line exceeds {} byte limit, dropping connection",
+                MAX_LINE_BYTES
+            );
+            self.send(&Response::Error {
+                message: format!("line exceeds {} byte limit", MAX_LINE_BYTES),
+            });
+            self.is_alive = false;
+        }
+    }
+    fn handle_line(&mut self, line: &[u8]) {
+        if line.is_empty() {
+            return;
+        }
+        match serde_json::from_slice::<Command>(line) {
+            Ok(command) => {
+                // println!("Got command: {:?}", command);
+                match command {
+                    Command::Lock {
+                        id,
+                        timeout_ms,
+                        lease_ms,
+                        mode,
+                    } => {
+                        let success = self.lock(&id, timeout_ms, lease_ms, mode);
+                        self.send(&Response::LockResponse { id, success });
+                    }
+                    Command::Renew { id, lease_ms } => {
+                        let success = self.renew(&id, lease_ms);
+                        self.send(&Response::RenewResponse { id, success });
+                    }
+                    Command::Release { id } => {
+                        let success = self.release(&id);
+                        self.send(&Response::ReleaseResponse { id, success });
+                    }
+                    Command::Check { id } => {
+                        let (mode, holders) = self.state.status(&id);
+                        self.send(&Response::CheckResponse { id, mode, holders });
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!("Cannot deserialize: {:?}", e);
+                self.send(&Response::Error {
+                    message: format!("cannot deserialize command: {}", e),
+                });
+            }
+        }
+    }
+    fn send<T: ?Sized + Serialize>(&mut self, payload: &T) -> bool {
+        let payload = serde_json::to_string(payload).unwrap() + "\n";
+
+        self.stream.write_all(payload.as_bytes()).unwrap();
+        let result = self.stream.flush();
+
+        match result {
+            Ok(_) => true,
+            Err(e) => {
+                eprintln!("Cannot send response: {:?}", e);
+                self.is_alive = false;
+                false
+            }
+        }
+    }
+    fn release_all(&mut self) {
+        self.locks.keys().for_each(|mutex_id| {
+            self.state.release(self.id, mutex_id);
+        });
+        self.locks.clear();
+        self.state.cancel_waiting(self.id);
+    }
+    fn release(&mut self, mutex_id: &MutexID) -> bool {
+        if !self.locks.contains_key(mutex_id) {
+            false
+        } else {
+            self.locks.remove(mutex_id);
+            self.state.release(self.id, mutex_id);
+            true
+        }
+    }
+    fn lock(
+        &mut self,
+        mutex_id: &MutexID,
+        timeout_ms: Option<u64>,
+        lease_ms: Option<u64>,
+        mode: LockMode,
+    ) -> bool {
+        // Re-entrant only when the held mode is compatible with what's being
+        // asked for again, and only after confirming with `Server` that we're
+        // still the actual holder: a leased hold can silently expire and be
+        // reaped/re-granted to someone else between calls, and this cache has
+        // no way to learn that on its own. If the cache is stale, drop it and
+        // fall through to a real `Server::lock` attempt instead of trusting it.
+        if self.locks.get(mutex_id) == Some(&mode) {
+            if self.state.is_holder(self.id, mutex_id, mode) {
+                return true;
+            }
+            self.locks.remove(mutex_id);
+        }
+
+        let timeout = timeout_ms.map(Duration::from_millis);
+        let lease = lease_ms.map(Duration::from_millis);
+        match self.state.lock(self.id, mutex_id, timeout, lease, mode) {
+            Ok(()) => {
+                self.locks.insert(mutex_id.clone(), mode);
+                true
+            }
+            Err(()) => false,
+        }
+    }
+    fn renew(&mut self, mutex_id: &MutexID, lease_ms: Option<u64>) -> bool {
+        if !self.locks.contains_key(mutex_id) {
+            return false;
+        }
+        let lease = lease_ms.map(Duration::from_millis);
+        self.state.renew(self.id, mutex_id, lease)
+    }
+}
+
+pub type MutexID = String;
+
+/// A queued waiter for a contended mutex in `--fair` mode. Each ticket owns
+/// its own condvar so a release can wake exactly the waiters that might now
+/// be grantable instead of every thread blocked on that mutex. `mode` lets
+/// `is_at_front` let several `Shared` tickets through together instead of
+/// serializing readers one at a time behind each other.
+#[derive(Clone)]
+struct Ticket {
+    id: u64,
+    conn: ConnectionID,
+    mode: LockMode,
+    condvar: Arc<Condvar>,
+}
+
+/// The current holder(s) of a mutex, modeled on `std::sync::RwLock`: either a
+/// single exclusive writer, or a set of shared readers each with their own
+/// lease. `expires` is `None` for a lease-less hold (the original behavior:
+/// held until explicit `Release` or disconnect).
+enum Holder {
+    Exclusive {
+        conn: ConnectionID,
+        expires: Option<Instant>,
+    },
+    Shared(HashMap<ConnectionID, Option<Instant>>),
+}
+
+impl Holder {
+    fn mode(&self) -> LockMode {
+        match self {
+            Holder::Exclusive { .. } => LockMode::Exclusive,
+            Holder::Shared(_) => LockMode::Shared,
+        }
+    }
+    fn holder_count(&self) -> usize {
+        match self {
+            Holder::Exclusive { .. } => 1,
+            Holder::Shared(holders) => holders.len(),
+        }
+    }
+}
+
+/// RAII registration for a thread blocked in the non-`--fair` `Server::lock`
+/// loop. Dropping it (on grant, timeout, or panic) deregisters the waiter so
+/// `mutex_waiters` doesn't keep a condvar entry alive once nobody is waiting
+/// on it anymore.
+struct WaiterGuard<'a> {
+    server: &'a Server,
+    mutex: &'a MutexID,
+    condvar: Arc<Condvar>,
+}
+
+impl<'a> WaiterGuard<'a> {
+    fn new(server: &'a Server, mutex: &'a MutexID) -> Self {
+        let condvar = server.register_waiter(mutex);
+        Self {
+            server,
+            mutex,
+            condvar,
+        }
+    }
+    fn condvar(&self) -> Arc<Condvar> {
+        self.condvar.clone()
+    }
+}
+
+impl Drop for WaiterGuard<'_> {
+    fn drop(&mut self) {
+        self.server.deregister_waiter(self.mutex);
+    }
+}
+
+/// A mutex's condvar in non-`--fair` mode, plus how many threads are
+/// currently registered against it — lets `deregister_waiter` evict the
+/// entry once the last one leaves.
+struct Waiters {
+    condvar: Arc<Condvar>,
+    count: usize,
+}
+
+#[derive(Clone)]
+pub struct Server {
+    mutex_to_conn: Arc<Mutex<HashMap<MutexID, Holder>>>,
+    mutex_waiters: Arc<Mutex<HashMap<MutexID, Waiters>>>,
+    mutex_queue: Arc<Mutex<HashMap<MutexID, VecDeque<Ticket>>>>,
+    next_id: Arc<Mutex<ConnectionID>>,
+    next_ticket: Arc<Mutex<u64>>,
+    fair: bool,
+    tls_acceptor: Option<Arc<TlsAcceptor>>,
+}
+
+impl Default for Server {
+    fn default() -> Self {
+        Self {
+            next_id: Arc::new(Mutex::new(1)),
+            next_ticket: Arc::new(Mutex::new(1)),
+            mutex_to_conn: Default::default(),
+            mutex_waiters: Default::default(),
+            mutex_queue: Default::default(),
+            fair: false,
+            tls_acceptor: None,
+        }
+    }
+}
+impl Server {
+    pub fn new(fair: bool, tls_acceptor: Option<Arc<TlsAcceptor>>) -> Self {
+        Self {
+            fair,
+            tls_acceptor,
+            ..Default::default()
+        }
+    }
+    fn generate_id(&self) -> usize {
+        let mut guard = self.next_id.lock().unwrap();
+        if *guard == usize::MAX {
+            *guard = 1;
+        } else {
+            *guard += 1;
+        }
+        *guard
+    }
+    /// Binds `bind` and serves forever. See `serve_on` to reuse an existing
+    /// listener (e.g. one bound to an ephemeral port for tests).
+    pub fn serve(&mut self, bind: &str) {
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind(bind).unwrap();
+        self.serve_on(listener);
+    }
+    /// Runs the accept loop and the lease-expiry reaper over an already-bound
+    /// `TcpListener`. Never returns.
+    pub fn serve_on(&mut self, listener: std::net::TcpListener) {
+        let reaper_state = self.clone();
+        thread::spawn(move || loop {
+            thread::sleep(LEASE_REAP_INTERVAL);
+            reaper_state.reap_expired();
+        });
+
+        for stream in listener.incoming() {
+            let stream = stream.unwrap();
+            stream.set_nodelay(true).unwrap();
+            let state = self.clone();
+            let tls_acceptor = self.tls_acceptor.clone();
+
+            let id = self.generate_id();
+
+            thread::spawn(move || {
+                let stream: Box<dyn Transport> = match tls_acceptor {
+                    Some(acceptor) => match acceptor.accept(stream) {
+                        Ok(tls_stream) => Box::new(tls_stream),
+                        Err(e) => {
+                            eprintln!("TLS handshake failed: {:?}", e);
+                            return;
+                        }
+                    },
+                    None => Box::new(stream),
+                };
+                let mut conn = Connection::new(state, id, stream);
+                conn.cycle();
+            });
+        }
+    }
+
+    pub fn lock(
+        &self,
+        conn: ConnectionID,
+        mutex: &MutexID,
+        timeout: Option<Duration>,
+        lease: Option<Duration>,
+        mode: LockMode,
+    ) -> Result<(), ()> {
+        if self.fair {
+            return self.lock_fair(conn, mutex, timeout, lease, mode);
+        }
+
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let mut locks = self.mutex_to_conn.lock().unwrap();
+        // Registered lazily: a request that's immediately grantable never
+        // becomes a waiter at all, and this guard's `Drop` deregisters it on
+        // every exit path (grant, timeout) so the entry doesn't outlive the
+        // last thread actually waiting on it.
+        let mut waiter: Option<WaiterGuard> = None;
+        loop {
+            if Self::can_grant(&locks, mutex, mode) {
+                Self::grant(&mut locks, mutex, conn, mode, lease);
+                println!("{} active, acquired [{}]", locks.len(), mutex);
+                return Ok(());
+            }
+
+            let condvar = waiter
+                .get_or_insert_with(|| WaiterGuard::new(self, mutex))
+                .condvar();
+            locks = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        return Err(());
+                    }
+                    let (guard, timeout_result) = condvar.wait_timeout(locks, remaining).unwrap();
+                    if timeout_result.timed_out() {
+                        return Err(());
+                    }
+                    guard
+                }
+                None => condvar.wait(locks).unwrap(),
+            };
+        }
+    }
+    /// FIFO variant of `lock`: the connection enqueues a ticket and may only
+    /// acquire once it reaches the front of that mutex's queue.
+    fn lock_fair(
+        &self,
+        conn: ConnectionID,
+        mutex: &MutexID,
+        timeout: Option<Duration>,
+        lease: Option<Duration>,
+        mode: LockMode,
+    ) -> Result<(), ()> {
+        let deadline = timeout.map(|t| Instant::now() + t);
+        let ticket = self.enqueue(conn, mutex, mode);
+
+        let mut locks = self.mutex_to_conn.lock().unwrap();
+        loop {
+            if Self::can_grant(&locks, mutex, mode) && self.is_at_front(mutex, &ticket) {
+                Self::grant(&mut locks, mutex, conn, mode, lease);
+                self.dequeue_ticket(mutex, ticket.id);
+                println!("{} active, acquired [{}]", locks.len(), mutex);
+                // A `Shared` grant may have left the new front of the queue
+                // (another `Shared` ticket) immediately grantable too; wake it
+                // now instead of waiting for this connection to release, so
+                // readers coexist rather than being serialized one-at-a-time.
+                self.wake_waiters(mutex);
+                return Ok(());
+            }
+
+            locks = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        self.dequeue_ticket(mutex, ticket.id);
+                        // This ticket may have been blocking a now-compatible
+                        // waiter behind it (e.g. an `Exclusive` ticket ahead
+                        // of a `Shared` one); wake the queue instead of
+                        // leaving that waiter stalled until an unrelated
+                        // release or its own timeout.
+                        self.wake_waiters(mutex);
+                        return Err(());
+                    }
+                    let (guard, timeout_result) =
+                        ticket.condvar.wait_timeout(locks, remaining).unwrap();
+                    if timeout_result.timed_out() {
+                        self.dequeue_ticket(mutex, ticket.id);
+                        self.wake_waiters(mutex);
+                        return Err(());
+                    }
+                    guard
+                }
+                None => ticket.condvar.wait(locks).unwrap(),
+            };
+        }
+    }
+    /// A shared request is grantable when no exclusive holder exists; an
+    /// exclusive request is grantable only when there are zero holders.
+    fn can_grant(locks: &HashMap<MutexID, Holder>, mutex: &MutexID, mode: LockMode) -> bool {
+        matches!(
+            (locks.get(mutex), mode),
+            (None, _) | (Some(Holder::Shared(_)), LockMode::Shared)
+        )
+    }
+    fn grant(
+        locks: &mut HashMap<MutexID, Holder>,
+        mutex: &MutexID,
+        conn: ConnectionID,
+        mode: LockMode,
+        lease: Option<Duration>,
+    ) {
+        let expires = lease.map(|d| Instant::now() + d);
+        match mode {
+            LockMode::Exclusive => {
+                locks.insert(mutex.clone(), Holder::Exclusive { conn, expires });
+            }
+            LockMode::Shared => match locks.get_mut(mutex) {
+                Some(Holder::Shared(holders)) => {
+                    holders.insert(conn, expires);
+                }
+                _ => {
+                    let mut holders = HashMap::new();
+                    holders.insert(conn, expires);
+                    locks.insert(mutex.clone(), Holder::Shared(holders));
+                }
+            },
+        }
+    }
+    /// Extends the lease of a mutex this connection currently holds. Fails if
+    /// the connection isn't a current holder (e.g. its lease already expired
+    /// and someone else acquired the mutex).
+    pub fn renew(&self, conn: ConnectionID, mutex: &MutexID, lease: Option<Duration>) -> bool {
+        let mut locks = self.mutex_to_conn.lock().unwrap();
+        let expires = lease.map(|d| Instant::now() + d);
+        match locks.get_mut(mutex) {
+            Some(Holder::Exclusive {
+                conn: holder,
+                expires: e,
+            }) if *holder == conn => {
+                *e = expires;
+                true
+            }
+            Some(Holder::Shared(holders)) if holders.contains_key(&conn) => {
+                holders.insert(conn, expires);
+                true
+            }
+            _ => false,
+        }
+    }
+    /// Reports whether `conn` is currently a holder of `mutex` in `mode`.
+    /// Lets `Connection::lock`'s re-entrant short-circuit confirm its cached
+    /// grant is still real rather than trusting it blindly, since a leased
+    /// hold can expire and be reaped/re-granted to someone else in between.
+    fn is_holder(&self, conn: ConnectionID, mutex: &MutexID, mode: LockMode) -> bool {
+        let locks = self.mutex_to_conn.lock().unwrap();
+        match locks.get(mutex) {
+            Some(Holder::Exclusive { conn: holder, .. }) => {
+                mode == LockMode::Exclusive && *holder == conn
+            }
+            Some(Holder::Shared(holders)) => {
+                mode == LockMode::Shared && holders.contains_key(&conn)
+            }
+            None => false,
+        }
+    }
+    /// Reports the current mode and holder count for a mutex, or `(None, 0)`
+    /// if it is free.
+    pub fn status(&self, mutex: &MutexID) -> (Option<LockMode>, usize) {
+        let locks = self.mutex_to_conn.lock().unwrap();
+        match locks.get(mutex) {
+            Some(holder) => (Some(holder.mode()), holder.holder_count()),
+            None => (None, 0),
+        }
+    }
+    pub fn release(&self, conn: ConnectionID, mutex: &MutexID) -> bool {
+        let mut locks = self.mutex_to_conn.lock().unwrap();
+
+        let freed = match locks.get_mut(mutex) {
+            Some(Holder::Exclusive { conn: holder, .. }) => *holder == conn,
+            Some(Holder::Shared(holders)) => {
+                holders.remove(&conn);
+                holders.is_empty()
+            }
+            None => false,
+        };
+        if freed {
+            locks.remove(mutex);
+        }
+        println!("{} active, released [{}]", locks.len(), mutex);
+        drop(locks);
+
+        if freed {
+            self.wake_waiters(mutex);
+        }
+        true
+    }
+    /// Scans for leases past their expiry, releases them, and wakes any
+    /// waiters queued for the freed mutexes. Run periodically from a
+    /// background thread spawned in `serve`.
+    fn reap_expired(&self) {
+        let mut locks = self.mutex_to_conn.lock().unwrap();
+        let now = Instant::now();
+        let mut freed = Vec::new();
+
+        locks.retain(|mutex, holder| {
+            let still_held = match holder {
+                Holder::Exclusive { expires, .. } => !expires.is_some_and(|e| e <= now),
+                Holder::Shared(holders) => {
+                    holders.retain(|_, expires| !expires.is_some_and(|e| e <= now));
+                    !holders.is_empty()
+                }
+            };
+            if !still_held {
+                freed.push(mutex.clone());
+            }
+            still_held
+        });
+        let active = locks.len();
+        drop(locks);
+
+        for mutex in &freed {
+            println!("{} active, lease expired [{}]", active, mutex);
+            self.wake_waiters(mutex);
+        }
+    }
+    fn wake_waiters(&self, mutex: &MutexID) {
+        if self.fair {
+            if let Some(front) = self
+                .mutex_queue
+                .lock()
+                .unwrap()
+                .get(mutex)
+                .and_then(|q| q.front().cloned())
+            {
+                front.condvar.notify_one();
+            }
+        } else if let Some(waiters) = self.mutex_waiters.lock().unwrap().get(mutex) {
+            waiters.condvar.notify_all();
+        }
+    }
+    /// Registers a thread as waiting on `mutex`'s condvar, creating it on
+    /// first use. Paired with `deregister_waiter`, which removes the entry
+    /// once the last waiter leaves — see `WaiterGuard`.
+    fn register_waiter(&self, mutex: &MutexID) -> Arc<Condvar> {
+        let mut waiters = self.mutex_waiters.lock().unwrap();
+        let entry = waiters.entry(mutex.clone()).or_insert_with(|| Waiters {
+            condvar: Arc::new(Condvar::new()),
+            count: 0,
+        });
+        entry.count += 1;
+        entry.condvar.clone()
+    }
+    /// Undoes `register_waiter`: decrements the waiter count for `mutex` and
+    /// drops its condvar entry once nobody is left waiting, so a mutex that's
+    /// no longer contended doesn't keep an `Arc<Condvar>` around forever.
+    fn deregister_waiter(&self, mutex: &MutexID) {
+        let mut waiters = self.mutex_waiters.lock().unwrap();
+        if let Some(entry) = waiters.get_mut(mutex) {
+            entry.count -= 1;
+            if entry.count == 0 {
+                waiters.remove(mutex);
+            }
+        }
+    }
+    fn enqueue(&self, conn: ConnectionID, mutex: &MutexID, mode: LockMode) -> Ticket {
+        let id = {
+            let mut next = self.next_ticket.lock().unwrap();
+            let id = *next;
+            *next += 1;
+            id
+        };
+        let ticket = Ticket {
+            id,
+            conn,
+            mode,
+            condvar: Arc::new(Condvar::new()),
+        };
+        self.mutex_queue
+            .lock()
+            .unwrap()
+            .entry(mutex.clone())
+            .or_default()
+            .push_back(ticket.clone());
+        ticket
+    }
+    /// A ticket is "at front" once every ticket ahead of it in the queue is
+    /// compatible with it running concurrently: an `Exclusive` ticket must be
+    /// strictly first, while a run of `Shared` tickets are all simultaneously
+    /// "at front" of each other so they can be granted together rather than
+    /// one-at-a-time.
+    fn is_at_front(&self, mutex: &MutexID, ticket: &Ticket) -> bool {
+        let queues = self.mutex_queue.lock().unwrap();
+        let Some(queue) = queues.get(mutex) else {
+            return true;
+        };
+        for ahead in queue {
+            if ahead.id == ticket.id {
+                return true;
+            }
+            if ticket.mode == LockMode::Exclusive || ahead.mode == LockMode::Exclusive {
+                return false;
+            }
+        }
+        false
+    }
+    fn dequeue_ticket(&self, mutex: &MutexID, ticket_id: u64) {
+        let mut queues = self.mutex_queue.lock().unwrap();
+        if let Some(queue) = queues.get_mut(mutex) {
+            queue.retain(|t| t.id != ticket_id);
+            if queue.is_empty() {
+                queues.remove(mutex);
+            }
+        }
+    }
+    fn cancel_waiting(&self, conn: ConnectionID) {
+        let mut queues = self.mutex_queue.lock().unwrap();
+        queues.retain(|_, queue| {
+            queue.retain(|t| t.conn != conn);
+            !queue.is_empty()
+        });
+    }
+}
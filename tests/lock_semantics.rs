@@ -0,0 +1,247 @@
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use mutex_server::{Client, LockMode, Server};
+
+/// Binds the server on an ephemeral port and serves it on a background
+/// thread, returning the address clients can connect to.
+fn spawn_server(fair: bool) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let mut server = Server::new(fair, None);
+    thread::spawn(move || server.serve_on(listener));
+    addr
+}
+
+#[test]
+fn second_connection_fails_to_lock_a_held_mutex() {
+    let addr = spawn_server(false);
+    let mut holder = Client::connect(&addr).unwrap();
+    let mut contender = Client::connect(&addr).unwrap();
+
+    assert!(holder.lock("res", None, None, LockMode::Exclusive).unwrap());
+    assert!(!contender
+        .lock("res", Some(50), None, LockMode::Exclusive)
+        .unwrap());
+}
+
+#[test]
+fn release_frees_the_mutex_for_the_next_waiter() {
+    let addr = spawn_server(false);
+    let mut holder = Client::connect(&addr).unwrap();
+    let mut contender = Client::connect(&addr).unwrap();
+
+    assert!(holder.lock("res", None, None, LockMode::Exclusive).unwrap());
+    assert!(holder.release("res").unwrap());
+    assert!(contender
+        .lock("res", Some(200), None, LockMode::Exclusive)
+        .unwrap());
+}
+
+#[test]
+fn timeout_ms_reports_failure_after_the_deadline() {
+    let addr = spawn_server(false);
+    let mut holder = Client::connect(&addr).unwrap();
+    let mut contender = Client::connect(&addr).unwrap();
+
+    assert!(holder.lock("res", None, None, LockMode::Exclusive).unwrap());
+
+    let start = Instant::now();
+    assert!(!contender
+        .lock("res", Some(100), None, LockMode::Exclusive)
+        .unwrap());
+    assert!(start.elapsed() >= Duration::from_millis(100));
+}
+
+#[test]
+fn dropping_a_connection_auto_releases_its_locks() {
+    let addr = spawn_server(false);
+    {
+        let mut holder = Client::connect(&addr).unwrap();
+        assert!(holder.lock("res", None, None, LockMode::Exclusive).unwrap());
+    } // `holder` drops here, closing the socket and triggering `release_all`
+
+    let mut contender = Client::connect(&addr).unwrap();
+    assert!(contender
+        .lock("res", Some(500), None, LockMode::Exclusive)
+        .unwrap());
+}
+
+#[test]
+fn shared_locks_allow_multiple_concurrent_readers() {
+    let addr = spawn_server(false);
+    let mut reader_a = Client::connect(&addr).unwrap();
+    let mut reader_b = Client::connect(&addr).unwrap();
+
+    assert!(reader_a.lock("res", None, None, LockMode::Shared).unwrap());
+    assert!(reader_b
+        .lock("res", Some(200), None, LockMode::Shared)
+        .unwrap());
+
+    let (mode, holders) = reader_a.check("res").unwrap();
+    assert_eq!(mode, Some(LockMode::Shared));
+    assert_eq!(holders, 2);
+}
+
+#[test]
+fn exclusive_lock_is_blocked_by_an_existing_shared_reader() {
+    let addr = spawn_server(false);
+    let mut reader = Client::connect(&addr).unwrap();
+    let mut writer = Client::connect(&addr).unwrap();
+
+    assert!(reader.lock("res", None, None, LockMode::Shared).unwrap());
+    assert!(!writer
+        .lock("res", Some(50), None, LockMode::Exclusive)
+        .unwrap());
+}
+
+#[test]
+fn holding_shared_does_not_let_a_connection_escalate_to_exclusive_with_other_readers_present() {
+    let addr = spawn_server(false);
+    let mut reader_a = Client::connect(&addr).unwrap();
+    let mut reader_b = Client::connect(&addr).unwrap();
+
+    assert!(reader_a.lock("res", None, None, LockMode::Shared).unwrap());
+    assert!(reader_b.lock("res", None, None, LockMode::Shared).unwrap());
+
+    // `reader_a` already holds `Shared` on "res"; asking for `Exclusive`
+    // while `reader_b` is still attached must not short-circuit to success.
+    assert!(!reader_a
+        .lock("res", Some(50), None, LockMode::Exclusive)
+        .unwrap());
+
+    let (mode, holders) = reader_a.check("res").unwrap();
+    assert_eq!(mode, Some(LockMode::Shared));
+    assert_eq!(holders, 2);
+}
+
+#[test]
+fn an_expired_lease_is_reaped_and_frees_the_mutex() {
+    let addr = spawn_server(false);
+    let mut holder = Client::connect(&addr).unwrap();
+    let mut contender = Client::connect(&addr).unwrap();
+
+    assert!(holder
+        .lock("res", None, Some(50), LockMode::Exclusive)
+        .unwrap());
+    assert!(contender
+        .lock("res", Some(500), None, LockMode::Exclusive)
+        .unwrap());
+}
+
+#[test]
+fn renew_extends_a_lease_past_its_original_expiry() {
+    let addr = spawn_server(false);
+    let mut holder = Client::connect(&addr).unwrap();
+    let mut contender = Client::connect(&addr).unwrap();
+
+    assert!(holder
+        .lock("res", None, Some(150), LockMode::Exclusive)
+        .unwrap());
+    assert!(holder.renew("res", Some(500)).unwrap());
+
+    assert!(!contender
+        .lock("res", Some(150), None, LockMode::Exclusive)
+        .unwrap());
+}
+
+#[test]
+fn a_stale_cached_grant_is_not_trusted_after_its_lease_expires_and_is_reaped() {
+    let addr = spawn_server(false);
+    let mut holder = Client::connect(&addr).unwrap();
+    let mut contender = Client::connect(&addr).unwrap();
+
+    assert!(holder
+        .lock("res", None, Some(50), LockMode::Exclusive)
+        .unwrap());
+
+    // Let the lease expire and the background reaper sweep it, then have
+    // someone else actually take the mutex.
+    thread::sleep(Duration::from_millis(200));
+    assert!(contender
+        .lock("res", None, None, LockMode::Exclusive)
+        .unwrap());
+
+    // `holder`'s local cache still thinks it holds "res" `Exclusive`, but
+    // `contender` is the real current holder now — re-issuing the identical
+    // `Lock` must not short-circuit to a false `success: true`.
+    assert!(!holder
+        .lock("res", Some(50), None, LockMode::Exclusive)
+        .unwrap());
+}
+
+#[test]
+fn fair_mode_grants_queued_shared_readers_concurrently_not_one_at_a_time() {
+    let addr = spawn_server(true);
+    let mut writer = Client::connect(&addr).unwrap();
+    assert!(writer.lock("res", None, None, LockMode::Exclusive).unwrap());
+
+    let (tx_a, rx_a) = mpsc::channel();
+    let addr_a = addr.clone();
+    thread::spawn(move || {
+        let mut reader = Client::connect(&addr_a).unwrap();
+        tx_a.send(reader.lock("res", Some(2_000), None, LockMode::Shared))
+            .unwrap();
+    });
+    thread::sleep(Duration::from_millis(50));
+
+    let (tx_b, rx_b) = mpsc::channel();
+    let addr_b = addr.clone();
+    thread::spawn(move || {
+        let mut reader = Client::connect(&addr_b).unwrap();
+        tx_b.send(reader.lock("res", Some(2_000), None, LockMode::Shared))
+            .unwrap();
+    });
+    thread::sleep(Duration::from_millis(50));
+
+    assert!(writer.release("res").unwrap());
+
+    // Both readers are queued behind the writer; once it releases they
+    // should be granted together, not serialized behind each other waiting
+    // for the reader ahead of them to disconnect.
+    assert!(rx_a.recv_timeout(Duration::from_millis(300)).unwrap().unwrap());
+    assert!(rx_b.recv_timeout(Duration::from_millis(300)).unwrap().unwrap());
+}
+
+#[test]
+fn a_timed_out_fair_ticket_wakes_the_next_compatible_waiter() {
+    let addr = spawn_server(true);
+    let mut reader = Client::connect(&addr).unwrap();
+    assert!(reader.lock("res", None, None, LockMode::Shared).unwrap());
+
+    // This `Exclusive` ticket queues ahead of the `Shared` waiter below and
+    // will time out, since `reader` never releases.
+    let (tx_writer, rx_writer) = mpsc::channel();
+    let addr_writer = addr.clone();
+    thread::spawn(move || {
+        let mut writer = Client::connect(&addr_writer).unwrap();
+        tx_writer
+            .send(writer.lock("res", Some(100), None, LockMode::Exclusive))
+            .unwrap();
+    });
+    thread::sleep(Duration::from_millis(30));
+
+    let (tx_reader, rx_reader) = mpsc::channel();
+    let addr_reader = addr.clone();
+    thread::spawn(move || {
+        let mut reader = Client::connect(&addr_reader).unwrap();
+        tx_reader
+            .send(reader.lock("res", Some(2_000), None, LockMode::Shared))
+            .unwrap();
+    });
+
+    assert!(!rx_writer
+        .recv_timeout(Duration::from_millis(500))
+        .unwrap()
+        .unwrap());
+
+    // Once the `Exclusive` ticket ahead of it times out and is dequeued, the
+    // queued `Shared` waiter is immediately grantable (the `Shared` holder
+    // above never released) and must be woken well within its own timeout.
+    assert!(rx_reader
+        .recv_timeout(Duration::from_millis(400))
+        .unwrap()
+        .unwrap());
+}